@@ -1,12 +1,16 @@
 use calamine::{open_workbook, Reader, Xlsx};
 use clap::{command, Parser};
+use glob::Pattern;
+use rayon::prelude::*;
 use std::{
     error::Error,
     fs, io,
+    io::Write,
     path::{Component, Path, PathBuf},
     process::exit,
     vec,
 };
+use walkdir::WalkDir;
 use {directories::UserDirs, lazy_regex::*};
 
 fn handle_exit(exit_code: i32, show_exit_message: bool) -> ! {
@@ -24,7 +28,7 @@ fn handle_message_output(message: &str, print_to_stdout: bool) {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum HeadersMode {
     Combine,
     Remove,
@@ -67,6 +71,143 @@ struct Args {
     /// Skip the `press enter to exit` prompt
     #[arg(short = 's', long)]
     skip_waiting: bool,
+
+    /// Number of threads to use when reading spreadsheets
+    /// Default: number of logical CPUs (0 or unset)
+    #[arg(short = 't', long, default_value_t = 0)]
+    threads: usize,
+
+    /// Recursively walk the input folder, including subdirectories
+    #[arg(short = 'r', long)]
+    recursive: bool,
+
+    /// Comma-separated list of file extensions to process (e.g. csv,xlsx)
+    /// Default: all supported extensions
+    #[arg(long, value_delimiter = ',')]
+    include_ext: Option<Vec<String>>,
+
+    /// Comma-separated list of file extensions to skip (e.g. xlsb)
+    #[arg(long, value_delimiter = ',')]
+    exclude_ext: Option<Vec<String>>,
+
+    /// Glob pattern of files/directories to skip, can be repeated
+    /// (e.g. --exclude node_modules --exclude '.git')
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Combine columns by matching header name instead of column position,
+    /// building a union header across all files. Overrides `--headers-mode`.
+    #[arg(long)]
+    align_by_name: bool,
+
+    /// Read and concatenate every sheet in a workbook instead of just the first
+    #[arg(long)]
+    all_sheets: bool,
+
+    /// Prefix each row with a synthetic column holding its source sheet name
+    /// Only applies with `--all-sheets`
+    #[arg(long)]
+    sheet_column: bool,
+
+    /// Read a specific sheet by name instead of the first one
+    /// Takes precedence over `--all-sheets`
+    #[arg(long)]
+    sheet: Option<String>,
+
+    /// Output format: csv, xlsx, ods, jsonl, or parquet
+    /// Default: inferred from the `-o` file extension
+    #[arg(long)]
+    out_format: Option<String>,
+}
+
+/// Which sheet(s) of a workbook `data_from_excel` should read.
+#[derive(Debug, Clone)]
+enum SheetSelection {
+    /// The first sheet only, matching the tool's historical behavior.
+    First,
+    /// Every sheet, concatenated in workbook order.
+    All { sheet_column: bool },
+    /// A single sheet picked out by name.
+    Named(String),
+}
+
+/// True if `path`'s extension is one this tool knows how to read.
+fn is_supported_extension(path: &Path) -> bool {
+    let extension = match path.extension() {
+        Some(e) => e.to_string_lossy().to_lowercase(),
+        None => return false,
+    };
+
+    matches!(
+        extension.as_str(),
+        "csv" | "ods" | "xls" | "xlsx" | "xlsm" | "xlsb" | "xla" | "xlam"
+    )
+}
+
+/// A boxed `data_from_*`-shaped reader, as returned by `reader_for`.
+type ReaderFn = Box<dyn Fn(PathBuf) -> Result<Vec<Vec<String>>, Box<dyn Error>> + Send + Sync>;
+
+/// Per-file read outcome, keyed by its original (pre-sort) index so the
+/// parallel read phase's results can be restored to a deterministic order.
+type ReadResults = Vec<(usize, Result<Vec<Vec<String>>, String>)>;
+
+/// Returns the `data_from_*` function that can read `path`, chosen from its
+/// (case-insensitive) extension, or `None` if the extension isn't supported.
+fn reader_for(
+    path: &Path,
+    sheet_selection: &SheetSelection,
+    headers_mode: HeadersMode,
+) -> Option<ReaderFn> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.as_str() {
+        "csv" => Some(Box::new(data_from_csv)),
+        "ods" | "xls" | "xlsx" | "xlsm" | "xlsb" | "xla" | "xlam" => {
+            let sheet_selection = sheet_selection.clone();
+            Some(Box::new(move |path| {
+                data_from_excel(path, &sheet_selection, headers_mode)
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// True if any component of `path` *relative to `base_dir`* matches one of
+/// the exclude patterns. Components of `base_dir` itself (and anything above
+/// it, such as `/home/ci/node_modules_cache`) are never considered, so an
+/// exclude pattern can't accidentally match an ancestor of the scanned tree.
+fn path_excluded(path: &Path, base_dir: &Path, exclude_patterns: &[Pattern]) -> bool {
+    let relative = path.strip_prefix(base_dir).unwrap_or(path);
+    relative.components().any(|component| {
+        let component = component.as_os_str().to_string_lossy();
+        exclude_patterns
+            .iter()
+            .any(|pattern| pattern.matches(&component))
+    })
+}
+
+/// True if `path`'s extension is one this tool can read, isn't in
+/// `exclude_exts`, and is in `include_exts` when that allowlist is set.
+fn extension_allowed(
+    path: &Path,
+    include_exts: &Option<Vec<String>>,
+    exclude_exts: &[String],
+) -> bool {
+    if !is_supported_extension(path) {
+        return false;
+    }
+
+    let extension = match path.extension() {
+        Some(e) => e.to_string_lossy().to_lowercase(),
+        None => return false,
+    };
+
+    if let Some(include_exts) = include_exts {
+        if !include_exts.iter().any(|e| e == &extension) {
+            return false;
+        }
+    }
+
+    !exclude_exts.iter().any(|e| e == &extension)
 }
 
 fn data_from_csv(path: PathBuf) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
@@ -97,32 +238,85 @@ fn data_from_csv(path: PathBuf) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
     // Ok(vec![vec!["".to_string()]])
 }
 
-fn data_from_excel(path: PathBuf) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+fn data_from_excel(
+    path: PathBuf,
+    sheet_selection: &SheetSelection,
+    headers_mode: HeadersMode,
+) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
     let mut workbook: Xlsx<_> = match open_workbook(path) {
         Ok(s) => s,
         Err(e) => return Err(Box::new(e)),
     };
 
-    let binding = workbook.sheet_names();
-    let sheet_one_name = match binding.get(0) {
-        Some(s) => s,
-        None => return Err("Xslx file does not have any sheets".into()),
+    let sheet_names = workbook.sheet_names();
+
+    let selected_sheets: Vec<String> = match sheet_selection {
+        SheetSelection::First => {
+            let sheet_one_name = match sheet_names.get(0) {
+                Some(s) => s.clone(),
+                None => return Err("Xslx file does not have any sheets".into()),
+            };
+
+            if sheet_names.len() > 1 {
+                println!("Warning: A spreadsheet contains more than one sheet. This tool will only read the first sheet and ignore the rest.");
+            }
+
+            vec![sheet_one_name]
+        }
+        SheetSelection::All { .. } => sheet_names,
+        SheetSelection::Named(name) => {
+            if !sheet_names.contains(name) {
+                return Err(format!("Workbook does not contain a sheet named '{}'", name).into());
+            }
+            vec![name.clone()]
+        }
     };
 
-    if workbook.sheet_names().len() > 1 {
-        println!("Warning: A spreadsheet contains more than one sheet. This tool will only read the first sheet and ignore the rest.");
-    }
+    let sheet_column = matches!(sheet_selection, SheetSelection::All { sheet_column: true });
 
-    let sheet_one = workbook.worksheet_range(sheet_one_name)?;
+    let mut data: Vec<Vec<String>> = vec![];
 
-    // println!("{:?}", sheet_one);
-    let data = sheet_one
-        .rows()
-        .map(|r| {
-            let cells = r.iter().map(|c| c.to_string());
-            cells.collect::<Vec<String>>()
-        })
-        .collect::<Vec<Vec<String>>>();
+    for (sheet_index, sheet_name) in selected_sheets.iter().enumerate() {
+        let sheet = workbook.worksheet_range(sheet_name)?;
+
+        // println!("{:?}", sheet);
+        let mut rows = sheet
+            .rows()
+            .enumerate()
+            .map(|(row_index, r)| {
+                let mut cells = r.iter().map(|c| c.to_string()).collect::<Vec<String>>();
+                if sheet_column {
+                    let label = if row_index == 0 {
+                        "Sheet".to_string()
+                    } else {
+                        sheet_name.clone()
+                    };
+                    cells.insert(0, label);
+                }
+                cells
+            })
+            .collect::<Vec<Vec<String>>>();
+
+        // Mirror the top-level `--headers-mode` semantics across sheets within
+        // one workbook: `Ignore` keeps every row of every sheet untouched,
+        // while `Combine` (the default) and `Remove` keep only the first
+        // sheet's header row, the same way additional files drop theirs under
+        // `Combine`. Under `Remove` that remaining header row is stripped by
+        // the per-file logic in `main`, like any other file's header row.
+        match headers_mode {
+            HeadersMode::Ignore => data.append(&mut rows),
+            HeadersMode::Combine | HeadersMode::Remove => {
+                if sheet_index == 0 {
+                    data.append(&mut rows);
+                } else {
+                    if !rows.is_empty() {
+                        rows.remove(0);
+                    }
+                    data.append(&mut rows);
+                }
+            }
+        }
+    }
 
     Ok(data)
 }
@@ -184,21 +378,370 @@ pub fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
     normalized
 }
 
-fn save_to_csv(data: &Vec<Vec<String>>, destination: &PathBuf) -> Result<(), Box<dyn Error>> {
-    let mut writer = csv::Writer::from_path(destination)?;
+/// One source column's slot in the union header: `display` is what shows up
+/// in the combined output, `key` is what `combine_by_header_name` uses to
+/// decide whether two columns (from the same or different files) are "the
+/// same" column.
+struct HeaderSlot {
+    key: String,
+    display: String,
+}
+
+/// Disambiguates repeated header names within a single file's header row
+/// (e.g. two "Notes" columns from merged-cell artifacts) by displaying later
+/// occurrences as `Notes (2)`, `Notes (3)`, ... so every source column keeps
+/// its own slot in the union header instead of silently overwriting another
+/// column's data. Prints a warning for each duplicate it renames.
+///
+/// The disambiguated slot's `key` is NOT just its display text: a NUL byte
+/// can't occur in a CSV/Excel cell, so building it as the name, a NUL byte and
+/// a `dup<n>` marker guarantees this synthesized key can never collide with another file's
+/// genuine, unrelated column that happens to be named e.g. literally
+/// `"Notes (2)"`.
+fn disambiguate_headers(header_row: &[String], file_name: &str) -> Vec<HeaderSlot> {
+    let mut seen: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+
+    header_row
+        .iter()
+        .map(|name| {
+            let count = seen.entry(name.as_str()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                HeaderSlot {
+                    key: name.clone(),
+                    display: name.clone(),
+                }
+            } else {
+                let display = format!("{} ({})", name, count);
+                println!(
+                    "Warning: {} has a duplicate header '{}', reading it as '{}'",
+                    file_name, name, display
+                );
+                HeaderSlot {
+                    key: format!("{}\u{0}dup{}", name, count),
+                    display,
+                }
+            }
+        })
+        .collect()
+}
+
+/// Combine the per-file data by header name rather than column position.
+///
+/// Scans every file's header row to build a union `Vec<String>` of distinct
+/// header names in first-seen order, then remaps each data row into that
+/// layout by looking up each source column's header in the union. Missing
+/// columns are filled with an empty string, so every returned row (including
+/// the union header itself as the first row) has the same width.
+fn combine_by_header_name(paths: Vec<(usize, PathBuf)>, results: ReadResults) -> Vec<Vec<String>> {
+    // `master_keys` and `master_display` are kept in lockstep: `master_keys`
+    // is what column lookups match against (collision-proof for
+    // disambiguated names, see `disambiguate_headers`), `master_display` is
+    // what actually gets written out as the combined header row.
+    let mut master_keys: Vec<String> = vec![];
+    let mut master_display: Vec<String> = vec![];
 
-    data.iter().for_each(|row| {
-        match writer.write_record(row) {
-            Ok(_) => {}
+    // Disambiguate each file's header row once up front (so a file with a
+    // duplicate header name only gets warned about once, not once per pass),
+    // and fold the result into the union header as we go.
+    let disambiguated_headers: Vec<Option<Vec<HeaderSlot>>> = paths
+        .iter()
+        .zip(results.iter())
+        .map(|((_, path), (_, result))| {
+            let header_row = result.as_ref().ok().and_then(|data| data.get(0))?;
+            let file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "`error`".to_string());
+
+            let disambiguated = disambiguate_headers(header_row, &file_name);
+            for slot in &disambiguated {
+                if !master_keys.contains(&slot.key) {
+                    master_keys.push(slot.key.clone());
+                    master_display.push(slot.display.clone());
+                }
+            }
+
+            Some(disambiguated)
+        })
+        .collect();
+
+    let mut combined: Vec<Vec<String>> = vec![master_display.clone()];
+
+    for (((_, path), (_, result)), header_row) in
+        paths.into_iter().zip(results).zip(disambiguated_headers)
+    {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "`error`".to_string());
+
+        let spreadsheet_data = match result {
+            Ok(data) => data,
             Err(e) => {
-                println!("{}", e.to_string());
+                println!("Error occurred while reading file {} : {}", file_name, e);
+                continue;
             }
         };
-    });
+
+        let header_row = match header_row {
+            Some(h) => h,
+            None => {
+                println!("Skipping {} since it's empty", file_name);
+                continue;
+            }
+        };
+
+        let column_map: Vec<Option<usize>> = header_row
+            .iter()
+            .map(|slot| master_keys.iter().position(|k| k == &slot.key))
+            .collect();
+
+        for row in spreadsheet_data.into_iter().skip(1) {
+            let mut aligned = vec![String::new(); master_display.len()];
+            for (source_index, cell) in row.into_iter().enumerate() {
+                match column_map.get(source_index).copied().flatten() {
+                    Some(master_index) => aligned[master_index] = cell,
+                    None => println!(
+                        "Dropping column {} from {} : not present in the combined header",
+                        source_index, file_name
+                    ),
+                }
+            }
+            combined.push(aligned);
+        }
+    }
+
+    combined
+}
+
+/// The output formats `unspread` can write, chosen via `--out-format` or
+/// inferred from the `-o` file extension.
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Csv,
+    Xlsx,
+    Ods,
+    JsonLines,
+    Parquet,
+}
+
+impl OutputFormat {
+    /// Parses a user-supplied `--out-format` name, case-insensitively.
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(OutputFormat::Csv),
+            "xlsx" => Some(OutputFormat::Xlsx),
+            "ods" => Some(OutputFormat::Ods),
+            "jsonl" | "json-lines" | "ndjson" => Some(OutputFormat::JsonLines),
+            "parquet" => Some(OutputFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    /// Falls back on the `-o` file extension when `--out-format` is omitted.
+    fn infer_from_path(path: &Path) -> Self {
+        match path.extension().map(|e| e.to_string_lossy().to_lowercase()) {
+            Some(ext) if ext == "xlsx" => OutputFormat::Xlsx,
+            Some(ext) if ext == "ods" => OutputFormat::Ods,
+            Some(ext) if ext == "jsonl" || ext == "ndjson" => OutputFormat::JsonLines,
+            Some(ext) if ext == "parquet" => OutputFormat::Parquet,
+            _ => OutputFormat::Csv,
+        }
+    }
+}
+
+/// Writes a combined header row and data rows to a destination file.
+///
+/// Implementations own the atomic write: they must leave `destination`
+/// untouched on failure (see `write_atomic`).
+trait Writer {
+    fn write_all(&self, headers: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>>;
+}
+
+/// Writes `write_fn`'s output to a sibling `<file_name>.<rand>.tmp` file and
+/// renames it over `destination` only once `write_fn` succeeds, so a failed
+/// write never leaves a half-written `destination` behind.
+fn write_atomic(
+    destination: &Path,
+    write_fn: impl FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let parent = destination.parent().unwrap_or_else(|| Path::new("."));
+    let file_name = destination
+        .file_name()
+        .ok_or("Output path has no file name")?
+        .to_string_lossy();
+    let temp_path = parent.join(format!("{}.{}.tmp", file_name, rand::random::<u32>()));
+
+    if let Err(e) = write_fn(&temp_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    fs::rename(&temp_path, destination)?;
 
     Ok(())
 }
 
+struct CsvWriter {
+    destination: PathBuf,
+}
+
+impl Writer for CsvWriter {
+    fn write_all(&self, headers: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        write_atomic(&self.destination, |temp_path| {
+            let mut writer = csv::Writer::from_path(temp_path)?;
+            writer.write_record(headers)?;
+            for row in rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+            Ok(())
+        })
+    }
+}
+
+/// Writes a genuine XLSX (OOXML) workbook with a single worksheet holding
+/// the combined header and data rows. There is no ODS support here: ODS is
+/// an unrelated OpenDocument/zip format, not something `rust_xlsxwriter` can
+/// produce, so `writer_for` never routes `OutputFormat::Ods` to this writer.
+struct WorkbookWriter {
+    destination: PathBuf,
+}
+
+impl Writer for WorkbookWriter {
+    fn write_all(&self, headers: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        write_atomic(&self.destination, |temp_path| {
+            let mut workbook = rust_xlsxwriter::Workbook::new();
+            let worksheet = workbook.add_worksheet();
+
+            for (col, header) in headers.iter().enumerate() {
+                worksheet.write_string(0, col as u16, header)?;
+            }
+
+            for (row_index, row) in rows.iter().enumerate() {
+                for (col, cell) in row.iter().enumerate() {
+                    worksheet.write_string((row_index + 1) as u32, col as u16, cell)?;
+                }
+            }
+
+            workbook.save(temp_path)?;
+            Ok(())
+        })
+    }
+}
+
+/// Writes a genuine OpenDocument Spreadsheet (ODS) file with a single sheet
+/// holding the combined header and data rows.
+struct OdsWriter {
+    destination: PathBuf,
+}
+
+impl Writer for OdsWriter {
+    fn write_all(&self, headers: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        write_atomic(&self.destination, |temp_path| {
+            let mut workbook = spreadsheet_ods::WorkBook::new_empty();
+            let mut sheet = spreadsheet_ods::Sheet::new("Sheet1");
+
+            for (col, header) in headers.iter().enumerate() {
+                sheet.set_value(0, col as u32, header.clone());
+            }
+
+            for (row_index, row) in rows.iter().enumerate() {
+                for (col, cell) in row.iter().enumerate() {
+                    sheet.set_value((row_index + 1) as u32, col as u32, cell.clone());
+                }
+            }
+
+            workbook.push_sheet(sheet);
+            spreadsheet_ods::write_ods(&mut workbook, temp_path)?;
+            Ok(())
+        })
+    }
+}
+
+/// Writes one JSON object per data row, keyed by the combined header row.
+struct JsonLinesWriter {
+    destination: PathBuf,
+}
+
+impl Writer for JsonLinesWriter {
+    fn write_all(&self, headers: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        write_atomic(&self.destination, |temp_path| {
+            let mut file = fs::File::create(temp_path)?;
+
+            for row in rows {
+                // Not built through a `serde_json::Map`: without the `preserve_order` feature
+                // `serde_json::Map` is a `BTreeMap` and would serialize keys
+                // alphabetically instead of in header order, so the object
+                // is rendered by hand here to guarantee header order
+                // regardless of which `serde_json` features are enabled.
+                let mut line = String::from("{");
+                for (i, (header, cell)) in headers.iter().zip(row.iter()).enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    line.push_str(&serde_json::to_string(header)?);
+                    line.push(':');
+                    line.push_str(&serde_json::to_string(cell)?);
+                }
+                line.push('}');
+
+                writeln!(file, "{}", line)?;
+            }
+
+            file.flush()?;
+            Ok(())
+        })
+    }
+}
+
+/// Writes a Parquet file with one Utf8 column per header,
+/// using Arrow as the in-memory layer `parquet`'s writer expects.
+struct ParquetWriter {
+    destination: PathBuf,
+}
+
+impl Writer for ParquetWriter {
+    fn write_all(&self, headers: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        write_atomic(&self.destination, |temp_path| {
+            let fields: Vec<arrow::datatypes::Field> = headers
+                .iter()
+                .map(|h| arrow::datatypes::Field::new(h, arrow::datatypes::DataType::Utf8, true))
+                .collect();
+            let schema = std::sync::Arc::new(arrow::datatypes::Schema::new(fields));
+
+            let columns: Vec<std::sync::Arc<dyn arrow::array::Array>> = (0..headers.len())
+                .map(|col| {
+                    let values: Vec<Option<String>> =
+                        rows.iter().map(|row| row.get(col).cloned()).collect();
+                    std::sync::Arc::new(arrow::array::StringArray::from(values))
+                        as std::sync::Arc<dyn arrow::array::Array>
+                })
+                .collect();
+
+            let batch = arrow::record_batch::RecordBatch::try_new(schema.clone(), columns)?;
+
+            let file = fs::File::create(temp_path)?;
+            let mut writer = parquet::arrow::ArrowWriter::try_new(file, schema, None)?;
+            writer.write(&batch)?;
+            writer.close()?;
+            Ok(())
+        })
+    }
+}
+
+/// Picks the `Writer` implementation for `format`, writing to `destination`.
+fn writer_for(format: OutputFormat, destination: PathBuf) -> Result<Box<dyn Writer>, Box<dyn Error>> {
+    match format {
+        OutputFormat::Csv => Ok(Box::new(CsvWriter { destination })),
+        OutputFormat::Xlsx => Ok(Box::new(WorkbookWriter { destination })),
+        OutputFormat::Ods => Ok(Box::new(OdsWriter { destination })),
+        OutputFormat::JsonLines => Ok(Box::new(JsonLinesWriter { destination })),
+        OutputFormat::Parquet => Ok(Box::new(ParquetWriter { destination })),
+    }
+}
+
 fn main() {
     let args = Args::parse();
     let headers_mode = HeadersMode::from(args.headers_mode);
@@ -239,29 +782,91 @@ fn main() {
         handle_exit(1, !args.skip_waiting);
     }
 
-    let dir = match fs::read_dir(spreadsheet_folder) {
-        Ok(d) => d,
-        Err(e) => {
-            println!("Error opening the inputs folder: {}", e.to_string());
-            handle_exit(1, !args.skip_waiting);
-        }
+    let exclude_patterns: Vec<Pattern> = args
+        .exclude
+        .iter()
+        .filter_map(|p| match Pattern::new(p) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                println!("Invalid --exclude pattern '{}': {}", p, e.to_string());
+                None
+            }
+        })
+        .collect();
+
+    let include_exts: Option<Vec<String>> = args
+        .include_ext
+        .as_ref()
+        .map(|exts| exts.iter().map(|e| e.trim().to_lowercase()).collect());
+    let exclude_exts: Vec<String> = args
+        .exclude_ext
+        .as_ref()
+        .map(|exts| exts.iter().map(|e| e.trim().to_lowercase()).collect())
+        .unwrap_or_default();
+
+    let sheet_selection = match &args.sheet {
+        Some(name) => SheetSelection::Named(name.clone()),
+        None if args.all_sheets => SheetSelection::All {
+            sheet_column: args.sheet_column,
+        },
+        None => SheetSelection::First,
     };
 
-    let mut total_file_count = 0;
+    // Filter out folders and unsupported file types up front, then sort the
+    // remaining paths so the read/parse phase below can run out of order
+    // without changing which file ends up at `index == 0`.
+    let mut eligible_paths: Vec<PathBuf> = vec![];
 
-    let dir_entries = dir.filter_map(|x| {
-        total_file_count += 1;
-        x.ok()
-    });
+    if args.recursive {
+        let walker = WalkDir::new(&spreadsheet_folder)
+            .into_iter()
+            .filter_entry(|e| !path_excluded(e.path(), &spreadsheet_folder, &exclude_patterns));
 
-    let mut headers: Vec<String> = vec![];
-    let mut combined_spreadsheet_data: Vec<Vec<String>> = vec![];
+        for entry in walker {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    println!("Error walking directory tree: {}", e.to_string());
+                    continue;
+                }
+            };
+
+            if entry.file_type().is_dir() {
+                continue;
+            }
+
+            let path = entry.into_path();
+            if !extension_allowed(&path, &include_exts, &exclude_exts) {
+                println!("Unsupported file type: {}", path.display());
+                continue;
+            }
 
-    for (index, entry) in dir_entries.enumerate() {
-        // Filter out folders
-        match entry.file_type() {
-            Ok(f) => {
-                if f.is_dir() {
+            eligible_paths.push(path);
+        }
+    } else {
+        let dir = match fs::read_dir(&spreadsheet_folder) {
+            Ok(d) => d,
+            Err(e) => {
+                println!("Error opening the inputs folder: {}", e.to_string());
+                handle_exit(1, !args.skip_waiting);
+            }
+        };
+
+        for entry in dir.filter_map(|x| x.ok()) {
+            match entry.file_type() {
+                Ok(f) => {
+                    if f.is_dir() {
+                        println!(
+                            "Skipping directory {}",
+                            entry
+                                .file_name()
+                                .into_string()
+                                .unwrap_or("`error`".to_string())
+                        );
+                        continue;
+                    }
+                }
+                Err(_) => {
                     println!(
                         "Skipping directory {}",
                         entry
@@ -272,105 +877,176 @@ fn main() {
                     continue;
                 }
             }
-            Err(_) => {
-                println!(
-                    "Skipping directory {}",
-                    entry
-                        .file_name()
-                        .into_string()
-                        .unwrap_or("`error`".to_string())
-                );
-            }
-        }
 
-        let name_binding = entry.file_name();
-        let file_name = name_binding.as_os_str().to_string_lossy();
-
-        let spreadsheet_data = match {
-            if file_name.ends_with(".csv") {
-                data_from_csv(entry.path())
-            } else if file_name.ends_with(".ods")
-                | file_name.ends_with("xls")
-                | file_name.ends_with("xlsx")
-                | file_name.ends_with("xlsm")
-                | file_name.ends_with("xlsb")
-                | file_name.ends_with("xla")
-                | file_name.ends_with("xlam")
-            {
-                data_from_excel(entry.path())
-            } else {
-                println!("Unsupported file type: {}", file_name);
+            let path = entry.path();
+            if path_excluded(&path, &spreadsheet_folder, &exclude_patterns) {
                 continue;
             }
-        } {
-            Ok(data) => data,
-            Err(e) => {
-                println!(
-                    "Error occurred while reading file {} : {}",
-                    file_name,
-                    e.to_string()
-                );
+
+            if !extension_allowed(&path, &include_exts, &exclude_exts) {
+                println!("Unsupported file type: {}", path.display());
                 continue;
             }
-        };
 
-        // println!("{:?}", spreadsheet_data);
+            eligible_paths.push(path);
+        }
+    }
+
+    eligible_paths.sort();
+
+    let indexed_paths: Vec<(usize, PathBuf)> = eligible_paths.into_iter().enumerate().collect();
+
+    let thread_count = if args.threads == 0 {
+        num_cpus::get()
+    } else {
+        args.threads
+    };
+
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .build()
+    {
+        Ok(p) => p,
+        Err(e) => {
+            println!("Error building thread pool: {}", e.to_string());
+            handle_exit(1, !args.skip_waiting);
+        }
+    };
+
+    // `--align-by-name` needs each file's first row to be its header and the
+    // rest data, whatever `--headers-mode` says.
+    let reader_headers_mode = if args.align_by_name {
+        HeadersMode::Combine
+    } else {
+        headers_mode
+    };
+
+    // The expensive I/O- and parse-bound work happens here, in parallel and
+    // out of order. The order-sensitive header-dedup logic below stays
+    // single-threaded and runs only after the results are sorted back by
+    // their original index.
+    let mut read_results: ReadResults = pool.install(|| {
+        indexed_paths
+            .par_iter()
+            .map(|(index, path)| {
+                let result = match reader_for(path, &sheet_selection, reader_headers_mode) {
+                    Some(read_fn) => read_fn(path.clone()),
+                    None => Err("no reader available for this file type".into()),
+                };
+
+                (*index, result.map_err(|e| e.to_string()))
+            })
+            .collect()
+    });
+
+    read_results.sort_by_key(|(index, _)| *index);
+
+    let combined_spreadsheet_data = if args.align_by_name {
+        combine_by_header_name(indexed_paths, read_results)
+    } else {
+        let mut headers: Vec<String> = vec![];
+        let mut combined_spreadsheet_data: Vec<Vec<String>> = vec![];
+
+        for ((index, path), (_, result)) in indexed_paths.into_iter().zip(read_results) {
+            let file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "`error`".to_string());
+
+            let spreadsheet_data = match result {
+                Ok(data) => data,
+                Err(e) => {
+                    println!("Error occurred while reading file {} : {}", file_name, e);
+                    continue;
+                }
+            };
+
+            // println!("{:?}", spreadsheet_data);
+
+            // Save headers on first spreadsheet
+            if index == 0 {
+                headers = match spreadsheet_data.get(0) {
+                    Some(h) => h.to_owned(),
+                    None => {
+                        println!("Skipping {} since it's empty", file_name);
+                        continue;
+                    }
+                }
+            }
 
-        // Save headers on first spreadsheet
-        if index == 0 {
-            headers = match spreadsheet_data.get(0) {
-                Some(h) => h.to_owned(),
+            let first_row = match spreadsheet_data.get(0) {
+                Some(data) => data,
                 None => {
                     println!("Skipping {} since it's empty", file_name);
                     continue;
                 }
-            }
-        }
+            };
 
-        let first_row = match spreadsheet_data.get(0) {
-            Some(data) => data,
-            None => {
-                println!("Skipping {} since it's empty", file_name);
-                continue;
-            }
-        };
+            let mut temp = vec![];
+            let mut final_spreadsheet_data = match headers_mode {
+                HeadersMode::Ignore => spreadsheet_data,
+                HeadersMode::Remove => {
+                    if first_row.len() != headers.len() {
+                        temp = spreadsheet_data;
+                    } else {
+                        for header_pair in first_row.iter().zip(headers.iter()) {
+                            if header_pair.0 != header_pair.1 {
+                                temp = spreadsheet_data.clone();
+                                break;
+                            }
+                        }
 
-        let mut temp = vec![];
-        let mut final_spreadsheet_data = match headers_mode {
-            HeadersMode::Ignore => spreadsheet_data,
-            HeadersMode::Remove => {
-                if first_row.len() != headers.len() {
-                    temp = spreadsheet_data;
-                } else {
-                    for header_pair in first_row.iter().zip(headers.iter()) {
-                        if header_pair.0 != header_pair.1 {
+                        if temp.len() == 0 {
                             temp = spreadsheet_data.clone();
-                            break;
+                            temp.remove(0);
                         }
                     }
-
-                    if temp.len() == 0 {
+                    temp
+                }
+                HeadersMode::Combine => {
+                    if index == 0 {
+                        spreadsheet_data
+                    } else {
                         temp = spreadsheet_data.clone();
                         temp.remove(0);
+                        temp
                     }
                 }
-                temp
-            }
-            HeadersMode::Combine => {
-                if index == 0 {
-                    spreadsheet_data
-                } else {
-                    temp = spreadsheet_data.clone();
-                    temp.remove(0);
-                    temp
-                }
+            };
+
+            combined_spreadsheet_data.append(&mut final_spreadsheet_data)
+        }
+
+        combined_spreadsheet_data
+    };
+
+    let out_format = match &args.out_format {
+        Some(name) => match OutputFormat::parse(name) {
+            Some(format) => format,
+            None => {
+                println!("Unknown output format: {}", name);
+                handle_exit(1, !args.skip_waiting);
             }
-        };
+        },
+        None => OutputFormat::infer_from_path(&output_file),
+    };
 
-        combined_spreadsheet_data.append(&mut final_spreadsheet_data)
-    }
+    let headers = combined_spreadsheet_data.get(0).cloned().unwrap_or_default();
+    let rows = if combined_spreadsheet_data.is_empty() {
+        &combined_spreadsheet_data[..]
+    } else {
+        &combined_spreadsheet_data[1..]
+    };
+
+    let writer = match writer_for(out_format, output_file) {
+        Ok(w) => w,
+        Err(e) => {
+            println!("{}", e.to_string());
+            handle_exit(1, !args.skip_waiting);
+        }
+    };
 
-    match save_to_csv(&combined_spreadsheet_data, &output_file) {
+    match writer.write_all(&headers, rows) {
         Ok(_) => {
             println!("Success: {} lines written", combined_spreadsheet_data.len());
             handle_exit(0, !args.skip_waiting)